@@ -2,16 +2,27 @@
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/sparse-slot
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod prelude;
+pub mod secondary;
+#[cfg(feature = "serde")]
+mod serde_impl;
 
-use std::fmt::{Debug, Display, Formatter};
+pub use secondary::SecondarySlot;
+
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display, Formatter};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum SparseSlotError {
     IndexOutOfBounds(usize),
     Occupied(usize),
-    //    GenerationMismatch(u8),
+    GenerationMismatch(u8),
     IllegalZeroGeneration,
+    Full,
 }
 
 /// A fixed-size sparse collection that maintains optional values at specified indices.
@@ -40,7 +51,7 @@ pub struct Id {
 }
 
 impl Display for Id {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:0>4}:{:04X}", self.index, self.generation)
     }
 }
@@ -68,6 +79,25 @@ impl Id {
             generation: self.generation.wrapping_add(1),
         }
     }
+
+    /// Packs this `Id` into a single `u64`: the `generation` in the high 8 bits
+    /// and the `index` in the low 56 bits. Useful for passing handles across FFI
+    /// boundaries or serializing them into network packets.
+    #[must_use]
+    pub fn to_bits(self) -> u64 {
+        ((self.generation as u64) << 56) | (self.index as u64 & 0x00FF_FFFF_FFFF_FFFF)
+    }
+
+    /// Unpacks an `Id` previously produced by [`Id::to_bits`].
+    ///
+    /// Returns `None` if the encoded index does not fit in a `usize` on the
+    /// current target (e.g. the high bits of the index field are set on a 32-bit target).
+    #[must_use]
+    pub fn from_bits(bits: u64) -> Option<Self> {
+        let generation = (bits >> 56) as u8;
+        let index = bits & 0x00FF_FFFF_FFFF_FFFF;
+        usize::try_from(index).ok().map(|index| Self { index, generation })
+    }
 }
 
 impl From<((usize, u8),)> for Id {
@@ -79,6 +109,7 @@ impl From<((usize, u8),)> for Id {
 pub struct Iter<'a, T> {
     items: &'a [Entry<T>],
     next_index: Option<usize>,
+    next_back_index: Option<usize>,
 }
 
 impl<'a, T> Iterator for Iter<'a, T> {
@@ -88,7 +119,31 @@ impl<'a, T> Iterator for Iter<'a, T> {
         let current_index = self.next_index?;
         let entry = &self.items[current_index];
 
-        self.next_index = entry.next_index;
+        if self.next_index == self.next_back_index {
+            self.next_index = None;
+            self.next_back_index = None;
+        } else {
+            self.next_index = entry.next_index;
+        }
+
+        entry
+            .item
+            .as_ref()
+            .map(|item| (Id::new(current_index, entry.generation), item))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let current_index = self.next_back_index?;
+        let entry = &self.items[current_index];
+
+        if self.next_back_index == self.next_index {
+            self.next_index = None;
+            self.next_back_index = None;
+        } else {
+            self.next_back_index = entry.previous_index;
+        }
 
         entry
             .item
@@ -210,6 +265,9 @@ struct Entry<T> {
     pub item: Option<T>,
     pub next_index: Option<usize>,
     pub previous_index: Option<usize>,
+    /// Set once this slot's generation has exhausted the `u8` range. A retired
+    /// slot is permanently removed from the free list and never reused.
+    pub retired: bool,
 }
 
 impl<T> Default for Entry<T> {
@@ -219,6 +277,7 @@ impl<T> Default for Entry<T> {
             item: None,
             next_index: None,
             previous_index: None,
+            retired: false,
         }
     }
 }
@@ -227,6 +286,17 @@ impl<T> Default for Entry<T> {
 pub struct SparseSlot<T> {
     items: Vec<Entry<T>>,
     first_occupied: Option<usize>,
+    /// Tail of the index-ordered occupied list, kept alongside `first_occupied` so
+    /// `last_id` and reverse iteration don't need to scan.
+    last_occupied: Option<usize>,
+    /// Head of the singly-linked free list. Vacant entries reuse their
+    /// `next_index` field to point at the next vacant entry, so popping
+    /// this gives an `O(1)` slot for `insert` without scanning the occupied list.
+    free_head: Option<usize>,
+    /// Number of slots permanently retired after exhausting their generation.
+    retired_count: usize,
+    /// Number of occupied slots, kept in sync so `len()` is `O(1)`.
+    len: usize,
 }
 
 impl<T> SparseSlot<T> {
@@ -252,35 +322,32 @@ impl<T> SparseSlot<T> {
     pub fn new(capacity: usize) -> Self {
         let mut items = Vec::with_capacity(capacity);
         items.extend((0..capacity).map(|_| Entry::default()));
+
+        let mut free_head = None;
+        for index in (0..capacity).rev() {
+            items[index].next_index = free_head;
+            free_head = Some(index);
+        }
+
         Self {
             items,
             first_occupied: None,
+            last_occupied: None,
+            free_head,
+            retired_count: 0,
+            len: 0,
         }
     }
 
     // Mutation ------------------------------------------------------------------------------------
 
-    pub fn try_set(&mut self, id: Id, item: T) -> Result<(), SparseSlotError> {
-        if id.index >= self.items.len() {
-            return Err(SparseSlotError::IndexOutOfBounds(id.index));
-        }
-
-        // First, validate the entry
-        {
-            let entry = &self.items[id.index];
-            if entry.item.is_some() {
-                return Err(SparseSlotError::Occupied(id.index));
-            }
-            if entry.generation != id.generation {
-                // return Err(SparseSlotError::GenerationMismatch(entry.generation));
-            }
-        }
-
+    /// Links a freshly occupied `index` into the index-ordered occupied list.
+    fn link_occupied(&mut self, index: usize) {
         let mut prev_index = None;
         let mut next_index = self.first_occupied;
 
         while let Some(current) = next_index {
-            if current > id.index {
+            if current > index {
                 break;
             }
             prev_index = Some(current);
@@ -288,26 +355,182 @@ impl<T> SparseSlot<T> {
         }
 
         {
-            let entry = &mut self.items[id.index];
-            entry.item = Some(item);
-            entry.generation = id.generation;
+            let entry = &mut self.items[index];
             entry.previous_index = prev_index;
             entry.next_index = next_index;
         }
 
         if let Some(prev_idx) = prev_index {
-            self.items[prev_idx].next_index = Some(id.index);
+            self.items[prev_idx].next_index = Some(index);
         } else {
-            self.first_occupied = Some(id.index);
+            self.first_occupied = Some(index);
         }
 
         if let Some(next_idx) = next_index {
-            self.items[next_idx].previous_index = Some(id.index);
+            self.items[next_idx].previous_index = Some(index);
+        } else {
+            self.last_occupied = Some(index);
+        }
+
+        self.len += 1;
+    }
+
+    /// Pushes a freed `index` back onto the free list.
+    fn push_free(&mut self, index: usize) {
+        let entry = &mut self.items[index];
+        entry.previous_index = None;
+        entry.next_index = self.free_head;
+        self.free_head = Some(index);
+    }
+
+    /// Removes `index` from the free list, wherever it currently sits in the chain.
+    /// Every index that is claimed outside of `insert` itself (`try_set`, `reserve`,
+    /// `reserve_with`, or restoring a retired slot) must call this - otherwise the
+    /// claimed index's `next_index` field gets overwritten with occupied-list bookkeeping,
+    /// silently truncating the free chain and both double-allocating that index and
+    /// leaking everything that followed it in the chain.
+    fn unlink_free(&mut self, index: usize) {
+        if self.free_head == Some(index) {
+            self.free_head = self.items[index].next_index;
+            return;
+        }
+
+        let mut current = self.free_head;
+        while let Some(current_index) = current {
+            let next = self.items[current_index].next_index;
+            if next == Some(index) {
+                self.items[current_index].next_index = self.items[index].next_index;
+                return;
+            }
+            current = next;
         }
+    }
+
+    /// Called once an entry's item has been taken. Either retires the slot for
+    /// good - if its generation has exhausted the `u8` range - or advances its
+    /// generation and returns it to the free list for reuse.
+    fn finalize_vacated(&mut self, index: usize) {
+        if self.items[index].generation == u8::MAX {
+            let entry = &mut self.items[index];
+            entry.retired = true;
+            entry.previous_index = None;
+            entry.next_index = None;
+            self.retired_count += 1;
+        } else {
+            self.items[index].generation = self.items[index].generation.wrapping_add(1);
+            self.push_free(index);
+        }
+
+        self.len -= 1;
+    }
+
+    /// Directly marks `index` as permanently retired, removing it from the free list.
+    /// Used by the `serde` support to restore a slot that was already retired when it
+    /// was serialized, without replaying its 255 generation bumps.
+    #[cfg(feature = "serde")]
+    pub(crate) fn mark_retired(&mut self, index: usize) {
+        self.unlink_free(index);
+
+        let entry = &mut self.items[index];
+        entry.generation = u8::MAX;
+        entry.retired = true;
+        entry.item = None;
+        entry.previous_index = None;
+        entry.next_index = None;
+        self.retired_count += 1;
+    }
+
+    /// Validates `id` against an empty slot, links it into the occupied list and
+    /// stamps its generation, leaving `item` as `None` for the caller to fill in.
+    fn prepare_occupied(&mut self, id: Id) -> Result<(), SparseSlotError> {
+        if id.index >= self.items.len() {
+            return Err(SparseSlotError::IndexOutOfBounds(id.index));
+        }
+
+        if id.generation == 0 {
+            return Err(SparseSlotError::IllegalZeroGeneration);
+        }
+
+        // First, validate the entry
+        {
+            let entry = &self.items[id.index];
+            if entry.retired {
+                return Err(SparseSlotError::Occupied(id.index));
+            }
+            if entry.item.is_some() {
+                return Err(SparseSlotError::Occupied(id.index));
+            }
+            // Generation 0 means the slot has never been handed out, so any caller-supplied
+            // generation may claim it. Otherwise the caller must present the exact generation
+            // this slot was last vacated at - this is what closes the ABA reuse window.
+            if entry.generation != 0 && entry.generation != id.generation {
+                return Err(SparseSlotError::GenerationMismatch(entry.generation));
+            }
+        }
+
+        self.unlink_free(id.index);
+        self.link_occupied(id.index);
+        self.items[id.index].generation = id.generation;
 
         Ok(())
     }
 
+    pub fn try_set(&mut self, id: Id, item: T) -> Result<(), SparseSlotError> {
+        self.prepare_occupied(id)?;
+        self.items[id.index].item = Some(item);
+
+        Ok(())
+    }
+
+    /// Allocates `id`'s slot and hands back a mutable reference to construct the
+    /// value in place via `default`, avoiding a move in and out for types that are
+    /// expensive to move (large buffers, GPU handles).
+    pub fn reserve_with<F>(&mut self, id: Id, default: F) -> Result<&mut T, SparseSlotError>
+    where
+        F: FnOnce() -> T,
+    {
+        self.prepare_occupied(id)?;
+        let entry = &mut self.items[id.index];
+        entry.item = Some(default());
+
+        Ok(entry.item.as_mut().expect("just inserted"))
+    }
+
+    /// Like [`SparseSlot::reserve_with`], constructing the value with `T::default()`.
+    pub fn reserve(&mut self, id: Id) -> Result<&mut T, SparseSlotError>
+    where
+        T: Default,
+    {
+        self.reserve_with(id, T::default)
+    }
+
+    /// Removes and returns the value at `id` by swapping it out, the reference-based
+    /// counterpart to [`SparseSlot::reserve`]/[`SparseSlot::reserve_with`].
+    pub fn take(&mut self, id: Id) -> Option<T> {
+        self.remove(id)
+    }
+
+    /// Allocates a vacant slot itself and returns the freshly-generated `Id`.
+    ///
+    /// Unlike `try_set`, the caller does not need to pick an index or generation - a
+    /// free slot is popped from the internal free list in `O(1)`. Returns
+    /// `SparseSlotError::Full` if every slot is occupied or permanently retired.
+    pub fn insert(&mut self, item: T) -> Result<Id, SparseSlotError> {
+        let index = self.free_head.ok_or(SparseSlotError::Full)?;
+        self.free_head = self.items[index].next_index;
+
+        if self.items[index].generation == 0 {
+            // Generation 0 means "never handed out" and must never be stamped onto a live entry.
+            self.items[index].generation = 1;
+        }
+        let id = Id::new(index, self.items[index].generation);
+
+        self.link_occupied(index);
+        self.items[index].item = Some(item);
+
+        Ok(id)
+    }
+
     pub fn remove(&mut self, id: Id) -> Option<T> {
         let (prev_index, next_index) = {
             let entry = &self.items[id.index];
@@ -320,6 +543,9 @@ impl<T> SparseSlot<T> {
         if Some(id.index) == self.first_occupied {
             self.first_occupied = next_index;
         }
+        if Some(id.index) == self.last_occupied {
+            self.last_occupied = prev_index;
+        }
 
         if let Some(prev_idx) = prev_index {
             self.items[prev_idx].next_index = next_index;
@@ -328,24 +554,20 @@ impl<T> SparseSlot<T> {
             self.items[next_idx].previous_index = prev_index;
         }
 
-        let entry = &mut self.items[id.index];
-        let item = entry.item.take();
-        entry.generation = entry.generation.wrapping_add(1);
-        entry.next_index = None;
-        entry.previous_index = None;
+        let item = self.items[id.index].item.take();
+        self.finalize_vacated(id.index);
 
         item
     }
 
     pub fn clear(&mut self) {
-        for entry in &mut self.items {
-            if entry.item.take().is_some() {
-                entry.generation = entry.generation.wrapping_add(1);
-                entry.next_index = None;
-                entry.previous_index = None;
+        for index in 0..self.items.len() {
+            if self.items[index].item.take().is_some() {
+                self.finalize_vacated(index);
             }
         }
         self.first_occupied = None;
+        self.last_occupied = None;
     }
 
     // Mutation getters ------------------------------------------------------------------------------------
@@ -371,6 +593,7 @@ impl<T> SparseSlot<T> {
         Iter {
             items: &self.items,
             next_index: self.first_occupied,
+            next_back_index: self.last_occupied,
         }
     }
 
@@ -392,14 +615,17 @@ impl<T> SparseSlot<T> {
 
     pub fn drain(&mut self) -> impl Iterator<Item = (Id, T)> + '_ {
         let mut index = self.first_occupied;
-        std::iter::from_fn(move || {
+        self.first_occupied = None;
+        self.last_occupied = None;
+        core::iter::from_fn(move || {
             while let Some(current_index) = index {
                 let entry = &mut self.items[current_index];
                 index = entry.next_index;
 
                 if let Some(item) = entry.item.take() {
-                    entry.generation = entry.generation.wrapping_add(1);
-                    return Some((Id::new(current_index, entry.generation - 1), item));
+                    let generation = entry.generation;
+                    self.finalize_vacated(current_index);
+                    return Some((Id::new(current_index, generation), item));
                 }
             }
             None
@@ -414,7 +640,7 @@ impl<T> SparseSlot<T> {
 
     #[must_use]
     pub fn len(&self) -> usize {
-        self.items.iter().filter(|x| x.item.is_some()).count()
+        self.len
     }
 
     #[must_use]
@@ -422,6 +648,19 @@ impl<T> SparseSlot<T> {
         self.len() == 0
     }
 
+    /// Returns `true` if the slot at `index` has been permanently retired after
+    /// exhausting its generation, meaning it can never hold a value again.
+    #[must_use]
+    pub fn is_retired(&self, index: usize) -> bool {
+        self.items.get(index).is_some_and(|entry| entry.retired)
+    }
+
+    /// Number of slots permanently retired after exhausting their generation.
+    #[must_use]
+    pub fn retired_count(&self) -> usize {
+        self.retired_count
+    }
+
     #[must_use]
     pub fn first_id(&self) -> Option<Id> {
         self.first_occupied.map(|index| {
@@ -430,14 +669,12 @@ impl<T> SparseSlot<T> {
         })
     }
 
-    // TODO: This is not efficient, should have a self.last_occupied in the future
+    #[must_use]
     pub fn last_id(&self) -> Option<Id> {
-        self.items
-            .iter()
-            .enumerate()
-            .rev()
-            .find(|(_, entry)| entry.item.is_some())
-            .map(|(index, entry)| Id::new(index, entry.generation))
+        self.last_occupied.map(|index| {
+            let entry = &self.items[index];
+            Id::new(index, entry.generation)
+        })
     }
 
     // Getters ------------------------------------------------------------------------------------
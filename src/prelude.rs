@@ -0,0 +1,7 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/sparse-slot
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+pub use crate::{
+    Id, Iter, IterMut, Keys, SecondarySlot, SparseSlot, SparseSlotError, Values, ValuesMut,
+};
@@ -0,0 +1,159 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/sparse-slot
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::{Id, SparseSlot, SparseSlotError};
+use alloc::vec::Vec;
+use core::iter::Enumerate;
+use core::slice;
+
+struct Entry<T> {
+    generation: u8,
+    item: Option<T>,
+}
+
+impl<T> Default for Entry<T> {
+    fn default() -> Self {
+        Self {
+            generation: 0,
+            item: None,
+        }
+    }
+}
+
+pub struct Iter<'a, 'p, T, U> {
+    items: Enumerate<slice::Iter<'a, Entry<T>>>,
+    primary: &'p SparseSlot<U>,
+}
+
+impl<'a, 'p, T, U> Iterator for Iter<'a, 'p, T, U> {
+    type Item = (Id, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, entry) in self.items.by_ref() {
+            let Some(item) = entry.item.as_ref() else {
+                continue;
+            };
+            let id = Id::new(index, entry.generation);
+            if index < self.primary.capacity() && self.primary.get(id).is_some() {
+                return Some((id, item));
+            }
+        }
+        None
+    }
+}
+
+/// A companion map keyed by the `Id`s minted by a primary `SparseSlot<U>`.
+///
+/// `SecondarySlot<T>` lets callers attach extra per-entity data without widening the
+/// primary element type, similar to a sparse ECS component column. Reads, mutable
+/// access and iteration all take a reference to the primary `SparseSlot` and cross-check
+/// its live generation, so a value left behind after the primary entry was removed (and
+/// its slot reused with a new generation) is transparently treated as absent - the caller
+/// never has to remember to clean up the secondary map in step with the primary.
+///
+/// # Examples
+///
+/// ```rust
+/// use sparse_slot::{SparseSlot, SecondarySlot};
+///
+/// let mut primary: SparseSlot<&str> = SparseSlot::new(4);
+/// let mut names: SecondarySlot<u32> = SecondarySlot::new(4);
+///
+/// let id = primary.insert("entity").unwrap();
+/// names.insert(id, 42).unwrap();
+/// assert_eq!(names.get(&primary, id), Some(&42));
+///
+/// primary.remove(id);
+/// assert_eq!(names.get(&primary, id), None); // invalidated along with the primary entry
+/// ```
+pub struct SecondarySlot<T> {
+    items: Vec<Entry<T>>,
+    capacity: usize,
+}
+
+impl<T> SecondarySlot<T> {
+    /// Creates an empty `SecondarySlot` that can hold entries for `Id`s up to
+    /// `capacity`, matching the capacity of the primary `SparseSlot`. The backing
+    /// storage grows lazily as entries are inserted, rather than being preallocated.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Associates `value` with `id`, growing the backing storage if needed.
+    ///
+    /// Returns the previously associated value, if any. Returns
+    /// `SparseSlotError::IndexOutOfBounds` if `id.index()` is beyond the capacity
+    /// passed to [`SecondarySlot::new`].
+    pub fn insert(&mut self, id: Id, value: T) -> Result<Option<T>, SparseSlotError> {
+        if id.index >= self.capacity {
+            return Err(SparseSlotError::IndexOutOfBounds(id.index));
+        }
+
+        if id.index >= self.items.len() {
+            self.items.resize_with(id.index + 1, Entry::default);
+        }
+
+        let entry = &mut self.items[id.index];
+        entry.generation = id.generation;
+
+        Ok(entry.item.replace(value))
+    }
+
+    /// Removes and returns the value for `id`, if its generation still matches.
+    pub fn remove(&mut self, id: Id) -> Option<T> {
+        let entry = self.items.get_mut(id.index)?;
+        if entry.generation != id.generation {
+            return None;
+        }
+        entry.item.take()
+    }
+
+    /// Returns the value for `id`, or `None` if the stored generation doesn't match
+    /// *or* `primary` no longer holds a live entry for `id` - e.g. because the primary
+    /// entry was removed without a matching call into this map.
+    #[must_use]
+    #[inline(always)]
+    pub fn get<U>(&self, primary: &SparseSlot<U>, id: Id) -> Option<&T> {
+        if id.index >= primary.capacity() || primary.get(id).is_none() {
+            return None;
+        }
+        let entry = self.items.get(id.index)?;
+        if entry.generation != id.generation {
+            return None;
+        }
+        entry.item.as_ref()
+    }
+
+    /// Mutable counterpart to [`SecondarySlot::get`].
+    #[must_use]
+    #[inline(always)]
+    pub fn get_mut<U>(&mut self, primary: &SparseSlot<U>, id: Id) -> Option<&mut T> {
+        if id.index >= primary.capacity() || primary.get(id).is_none() {
+            return None;
+        }
+        let entry = self.items.get_mut(id.index)?;
+        if entry.generation != id.generation {
+            return None;
+        }
+        entry.item.as_mut()
+    }
+
+    /// Iterates the entries that are both stored here and still live in `primary`.
+    pub fn iter<'p, U>(&self, primary: &'p SparseSlot<U>) -> Iter<'_, 'p, T, U> {
+        Iter {
+            items: self.items.iter().enumerate(),
+            primary,
+        }
+    }
+
+    /// The capacity passed to [`SecondarySlot::new`], matching the primary `SparseSlot`.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,93 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/sparse-slot
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+//! `serde` support, enabled by the `serde` feature.
+//!
+//! `Id` is encoded as the single `u64` produced by [`Id::to_bits`]. `SparseSlot<T>`
+//! preserves each occupied slot's index *and* generation (not just a dense list of
+//! values), so that `Id`s saved before serialization still resolve with `get` after
+//! deserializing - `first_occupied` and the ordered-occupied list are rebuilt through
+//! the normal `try_set` path. Permanently retired slots are also preserved (by index),
+//! so a slot that had exhausted its generation before serialization stays retired
+//! after deserializing, rather than coming back as a fresh, reusable slot.
+use crate::{Id, SparseSlot};
+use alloc::format;
+use alloc::vec::Vec;
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+impl Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_bits().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = u64::deserialize(deserializer)?;
+        Id::from_bits(bits).ok_or_else(|| D::Error::custom("Id index out of range for usize"))
+    }
+}
+
+#[derive(Serialize)]
+struct SerializedEntryRef<'a, T> {
+    index: usize,
+    generation: u8,
+    value: &'a T,
+}
+
+#[derive(Deserialize)]
+struct SerializedEntry<T> {
+    index: usize,
+    generation: u8,
+    value: T,
+}
+
+#[derive(Deserialize)]
+struct SerializedSlot<T> {
+    capacity: usize,
+    entries: Vec<SerializedEntry<T>>,
+    retired: Vec<usize>,
+}
+
+impl<T: Serialize> Serialize for SparseSlot<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries: Vec<_> = self
+            .iter()
+            .map(|(id, value)| SerializedEntryRef {
+                index: id.index(),
+                generation: id.generation(),
+                value,
+            })
+            .collect();
+        let retired: Vec<usize> = (0..self.capacity())
+            .filter(|&index| self.is_retired(index))
+            .collect();
+
+        let mut state = serializer.serialize_struct("SparseSlot", 3)?;
+        state.serialize_field("capacity", &self.capacity())?;
+        state.serialize_field("entries", &entries)?;
+        state.serialize_field("retired", &retired)?;
+        state.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SparseSlot<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedSlot::<T>::deserialize(deserializer)?;
+        let mut slot = SparseSlot::new(serialized.capacity);
+
+        for entry in serialized.entries {
+            slot.try_set(Id::new(entry.index, entry.generation), entry.value)
+                .map_err(|err| D::Error::custom(format!("{err:?}")))?;
+        }
+
+        for index in serialized.retired {
+            slot.mark_retired(index);
+        }
+
+        Ok(slot)
+    }
+}
@@ -5,10 +5,21 @@
 
 use sparse_slot::prelude::*;
 
+#[test]
+fn id_bits_round_trip() {
+    let id = Id::new(0x00FF_FFFF_FFFF_FFFF, 0xAB);
+    assert_eq!(Id::from_bits(id.to_bits()), Some(id));
+
+    let zero = Id::new(0, 0);
+    assert_eq!(Id::from_bits(zero.to_bits()), Some(zero));
+
+    assert_eq!(Id::new(7, 3).to_bits(), 0x0300_0000_0000_0007);
+}
+
 #[test]
 fn basic_operations() {
     let mut slot = SparseSlot::new(3);
-    let id = Id::new(1, 0);
+    let id = Id::new(1, 1);
 
     assert!(slot.try_set(id, "hello").is_ok());
     assert_eq!(slot.get(id), Some(&"hello"));
@@ -20,7 +31,7 @@ fn basic_operations() {
 #[test]
 fn generation_handling() {
     let mut slot = SparseSlot::new(2);
-    let id1 = Id::new(1, 0);
+    let id1 = Id::new(1, 1);
 
     assert!(slot.try_set(id1, 42).is_ok());
     assert_eq!(slot.remove(id1), Some(42));
@@ -34,10 +45,91 @@ fn generation_handling() {
     assert_eq!(slot.get(id2), Some(&43));
 }
 
+#[test]
+fn generation_retirement() {
+    let mut slot: SparseSlot<i32> = SparseSlot::new(1);
+
+    // Generation 0 means "never handed out" and must never be stamped onto a live entry.
+    assert!(matches!(
+        slot.try_set(Id::new(0, 0), 1),
+        Err(SparseSlotError::IllegalZeroGeneration)
+    ));
+
+    let mut last_id = Id::new(0, 0);
+    for _ in 0..255 {
+        last_id = slot.insert(0).unwrap();
+        slot.remove(last_id);
+    }
+
+    // The 255th generation exhausted the u8 range, so the slot retires instead of wrapping.
+    assert!(slot.is_retired(0));
+    assert_eq!(slot.retired_count(), 1);
+
+    // A retired slot can never be handed out again, via insert or try_set.
+    assert!(matches!(slot.insert(2), Err(SparseSlotError::Full)));
+    assert!(slot.try_set(last_id, 3).is_err());
+}
+
+#[test]
+fn insert_reuses_freed_index_with_bumped_generation() {
+    let mut slot: SparseSlot<i32> = SparseSlot::new(2);
+
+    let id0 = slot.insert(1).unwrap();
+    let id1 = slot.insert(2).unwrap();
+    assert_eq!(id0.index(), 0);
+    assert_eq!(id1.index(), 1);
+
+    // Capacity exhausted: no free slots left for insert() to hand out.
+    assert!(matches!(slot.insert(3), Err(SparseSlotError::Full)));
+
+    slot.remove(id0);
+    let id0_again = slot.insert(4).unwrap();
+    assert_eq!(id0_again.index(), id0.index());
+    assert_eq!(id0_again.generation(), id0.generation() + 1);
+    assert_eq!(slot.get(id0_again), Some(&4));
+}
+
+#[test]
+fn insert_splices_reused_index_into_order() {
+    let mut slot: SparseSlot<&str> = SparseSlot::new(3);
+
+    let id0 = slot.insert("first").unwrap();
+    let id1 = slot.insert("second").unwrap();
+    let id2 = slot.insert("third").unwrap();
+
+    slot.remove(id1);
+    let id1_again = slot.insert("second again").unwrap();
+    assert_eq!(id1_again.index(), id1.index());
+
+    let items: Vec<_> = slot.iter().map(|(id, value)| (id, *value)).collect();
+    assert_eq!(
+        items,
+        vec![(id0, "first"), (id1_again, "second again"), (id2, "third")]
+    );
+}
+
+#[test]
+fn insert_coexists_with_try_set() {
+    let mut slot: SparseSlot<i32> = SparseSlot::new(4);
+
+    // Claim index 2 directly, bypassing insert()'s free list.
+    slot.try_set(Id::new(2, 1), 100).unwrap();
+
+    let inserted: Vec<_> = (0..3).map(|value| slot.insert(value).unwrap()).collect();
+
+    // insert() must never hand out the index try_set already claimed, and must still
+    // be able to reach every other free index (the free chain must stay intact).
+    assert!(inserted.iter().all(|id| id.index() != 2));
+    assert_eq!(slot.get(Id::new(2, 1)), Some(&100));
+
+    // All four slots are now occupied.
+    assert!(matches!(slot.insert(99), Err(SparseSlotError::Full)));
+}
+
 #[test]
 fn error_conditions() {
     let mut slot = SparseSlot::new(1);
-    let id = Id::new(0, 0);
+    let id = Id::new(0, 1);
 
     // Test double set
     assert!(slot.try_set(id, 1).is_ok());
@@ -47,7 +139,7 @@ fn error_conditions() {
     ));
 
     // Test out of bounds
-    let invalid_id = Id::new(999, 0);
+    let invalid_id = Id::new(999, 1);
     assert!(matches!(
         slot.try_set(invalid_id, 3),
         Err(SparseSlotError::IndexOutOfBounds(_))
@@ -57,8 +149,8 @@ fn error_conditions() {
 #[test]
 fn iteration() {
     let mut slot = SparseSlot::new(3);
-    let id0 = Id::new(1, 0);
-    let id2 = Id::new(2, 0);
+    let id0 = Id::new(1, 1);
+    let id2 = Id::new(2, 1);
 
     slot.try_set(id0, "first").unwrap();
     slot.try_set(id2, "third").unwrap();
@@ -82,8 +174,8 @@ fn iteration() {
 #[test]
 fn clear_and_capacity() {
     let mut slot = SparseSlot::new(2);
-    let id0 = Id::new(0, 0);
-    let id1 = Id::new(1, 0);
+    let id0 = Id::new(0, 1);
+    let id1 = Id::new(1, 1);
 
     slot.try_set(id0, 1).unwrap();
     slot.try_set(id1, 2).unwrap();
@@ -111,9 +203,9 @@ fn iterator_ownership() {
     let mut slot = SparseSlot::new(5);
 
     // Set up some values
-    slot.try_set(Id::new(0, 0), "first").unwrap();
-    slot.try_set(Id::new(2, 0), "second").unwrap();
-    slot.try_set(Id::new(4, 0), "third").unwrap();
+    slot.try_set(Id::new(0, 1), "first").unwrap();
+    slot.try_set(Id::new(2, 1), "second").unwrap();
+    slot.try_set(Id::new(4, 1), "third").unwrap();
 
     let collected: Vec<_> = slot.into_iter().collect();
     assert_eq!(collected.len(), 3);
@@ -132,9 +224,9 @@ fn iterator_ownership() {
 fn iterator_order() {
     let mut slot = SparseSlot::new(5);
 
-    let id0 = Id::new(0, 0);
-    let id2 = Id::new(2, 0);
-    let id4 = Id::new(4, 0);
+    let id0 = Id::new(0, 1);
+    let id2 = Id::new(2, 1);
+    let id4 = Id::new(4, 1);
 
     slot.try_set(id2, "second").unwrap();
     slot.try_set(id0, "first").unwrap();
@@ -156,9 +248,9 @@ fn iterator_order() {
 fn iterator_modifications() {
     let mut slot = SparseSlot::new(5);
 
-    let id0 = Id::new(0, 0);
-    let id2 = Id::new(2, 0);
-    let id4 = Id::new(4, 0);
+    let id0 = Id::new(0, 1);
+    let id2 = Id::new(2, 1);
+    let id4 = Id::new(4, 1);
 
     slot.try_set(id0, "first").unwrap();
     slot.try_set(id2, "second").unwrap();
@@ -181,8 +273,8 @@ fn iterator_modifications() {
 fn specialized_iterators() {
     let mut slot = SparseSlot::new(3);
 
-    let id0 = Id::new(0, 0);
-    let id1 = Id::new(1, 0);
+    let id0 = Id::new(0, 1);
+    let id1 = Id::new(1, 1);
 
     slot.try_set(id0, "first").unwrap();
     slot.try_set(id1, "second").unwrap();
@@ -207,25 +299,25 @@ fn specialized_iterators() {
 fn drain() {
     let mut slot = SparseSlot::new(3);
 
-    slot.try_set(Id::new(0, 0), "first").unwrap();
-    slot.try_set(Id::new(1, 0), "second").unwrap();
+    slot.try_set(Id::new(0, 1), "first").unwrap();
+    slot.try_set(Id::new(1, 1), "second").unwrap();
 
     let drained: Vec<_> = slot.drain().collect();
     assert_eq!(drained.len(), 2);
     assert!(slot.is_empty());
 
-    assert!(slot.try_set(Id::new(0, 0), "new").is_err());
-    assert!(slot.try_set(Id::new(0, 1), "new").is_ok());
+    assert!(slot.try_set(Id::new(0, 1), "new").is_err());
+    assert!(slot.try_set(Id::new(0, 2), "new").is_ok());
 }
 
 #[test]
 fn collect_into_slot() {
-    let items = vec![(Id::new(0, 0), "first"), (Id::new(1, 0), "second")];
+    let items = vec![(Id::new(0, 1), "first"), (Id::new(1, 1), "second")];
 
     let slot: SparseSlot<&str> = items.into_iter().collect();
     assert_eq!(slot.len(), 2);
-    assert_eq!(slot.get(Id::new(0, 0)), Some(&"first"));
-    assert_eq!(slot.get(Id::new(1, 0)), Some(&"second"));
+    assert_eq!(slot.get(Id::new(0, 1)), Some(&"first"));
+    assert_eq!(slot.get(Id::new(1, 1)), Some(&"second"));
 }
 
 #[test]
@@ -233,14 +325,157 @@ fn first_id() {
     let mut slot = SparseSlot::new(5);
     assert_eq!(slot.first_id(), None);
 
-    let id2 = Id::new(2, 0);
+    let id2 = Id::new(2, 1);
     slot.try_set(id2, "second").unwrap();
     assert_eq!(slot.first_id(), Some(id2));
 
-    let id0 = Id::new(0, 0);
+    let id0 = Id::new(0, 1);
     slot.try_set(id0, "first").unwrap();
     assert_eq!(slot.first_id(), Some(id0));
 
     slot.remove(id0);
     assert_eq!(slot.first_id(), Some(id2));
 }
+
+#[test]
+fn secondary_slot() {
+    let mut primary: SparseSlot<&str> = SparseSlot::new(3);
+    let mut secondary: SecondarySlot<u32> = SecondarySlot::new(3);
+
+    let id0 = primary.insert("first").unwrap();
+    let id1 = primary.insert("second").unwrap();
+
+    assert!(secondary.insert(id0, 10).unwrap().is_none());
+    assert!(secondary.insert(id1, 20).unwrap().is_none());
+
+    assert_eq!(secondary.get(&primary, id0), Some(&10));
+    assert_eq!(secondary.get(&primary, id1), Some(&20));
+
+    let mut values: Vec<_> = secondary.iter(&primary).map(|(_, value)| *value).collect();
+    values.sort_unstable();
+    assert_eq!(values, vec![10, 20]);
+
+    // Removing from the primary transparently invalidates the secondary entry,
+    // even though the secondary map was never told about the removal.
+    primary.remove(id0);
+    assert_eq!(secondary.get(&primary, id0), None);
+
+    let new_id0 = primary.insert("third").unwrap();
+    assert_eq!(secondary.get(&primary, new_id0), None);
+
+    assert_eq!(secondary.remove(id1), Some(20));
+    assert_eq!(secondary.get(&primary, id1), None);
+
+    assert!(matches!(
+        secondary.insert(Id::new(99, 1), 1),
+        Err(SparseSlotError::IndexOutOfBounds(_))
+    ));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_round_trip() {
+    let mut slot = SparseSlot::new(5);
+
+    slot.try_set(Id::new(1, 1), "first").unwrap();
+    slot.try_set(Id::new(3, 1), "second").unwrap();
+    slot.remove(Id::new(1, 1));
+    let id1 = Id::new(1, 2);
+    slot.try_set(id1, "third").unwrap();
+
+    let json = serde_json::to_string(&slot).unwrap();
+    let restored: SparseSlot<&str> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored.capacity(), slot.capacity());
+    assert_eq!(restored.len(), slot.len());
+    assert_eq!(restored.get(id1), Some(&"third"));
+    assert_eq!(restored.get(Id::new(3, 1)), Some(&"second"));
+    assert_eq!(restored.first_id(), slot.first_id());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_round_trip_preserves_retirement() {
+    let mut slot: SparseSlot<&str> = SparseSlot::new(2);
+
+    let mut retired_index = 0;
+    for _ in 0..255 {
+        let id = slot.insert("placeholder").unwrap();
+        retired_index = id.index();
+        slot.remove(id);
+    }
+    assert!(slot.is_retired(retired_index));
+
+    let survivor = slot.insert("alive").unwrap();
+
+    let json = serde_json::to_string(&slot).unwrap();
+    let mut restored: SparseSlot<&str> = serde_json::from_str(&json).unwrap();
+
+    assert!(restored.is_retired(retired_index));
+    assert_eq!(restored.retired_count(), slot.retired_count());
+    assert_eq!(restored.get(survivor), Some(&"alive"));
+    assert!(matches!(
+        restored.try_set(Id::new(retired_index, 1), "nope"),
+        Err(SparseSlotError::Occupied(_))
+    ));
+
+    // A retired slot must also be unreachable through insert()'s free list, not just
+    // rejected by try_set - every other slot is occupied, so this is Full rather than
+    // insert() silently resurrecting the retired index.
+    assert!(matches!(restored.insert("nope"), Err(SparseSlotError::Full)));
+}
+
+#[test]
+fn reserve_and_take() {
+    let mut slot: SparseSlot<Vec<i32>> = SparseSlot::new(3);
+    let id = Id::new(0, 1);
+
+    slot.reserve(id).unwrap().push(1);
+    slot.get_mut(id).unwrap().push(2);
+    assert_eq!(slot.get(id), Some(&vec![1, 2]));
+
+    // Reserving an already-occupied slot is rejected, just like try_set.
+    assert!(matches!(
+        slot.reserve(id),
+        Err(SparseSlotError::Occupied(_))
+    ));
+
+    assert_eq!(slot.take(id), Some(vec![1, 2]));
+    assert_eq!(slot.get(id), None);
+
+    let id2 = id.next();
+    let built = slot.reserve_with(id2, || vec![9, 9, 9]).unwrap();
+    assert_eq!(built, &vec![9, 9, 9]);
+    assert_eq!(slot.get(id2), Some(&vec![9, 9, 9]));
+}
+
+#[test]
+fn last_id_and_reverse_iteration() {
+    let mut slot = SparseSlot::new(5);
+    assert_eq!(slot.last_id(), None);
+
+    let id0 = Id::new(0, 1);
+    let id2 = Id::new(2, 1);
+    let id4 = Id::new(4, 1);
+
+    slot.try_set(id2, "second").unwrap();
+    assert_eq!(slot.last_id(), Some(id2));
+
+    slot.try_set(id4, "third").unwrap();
+    assert_eq!(slot.last_id(), Some(id4));
+
+    slot.try_set(id0, "first").unwrap();
+    assert_eq!(slot.last_id(), Some(id4));
+
+    let rev: Vec<_> = slot.iter().rev().collect();
+    assert_eq!(rev.len(), 3);
+    assert_eq!(rev[0].1, &"third");
+    assert_eq!(rev[1].1, &"second");
+    assert_eq!(rev[2].1, &"first");
+
+    assert_eq!(slot.iter().next_back(), Some((id4, &"third")));
+
+    slot.remove(id4);
+    assert_eq!(slot.last_id(), Some(id2));
+    assert_eq!(slot.len(), 2);
+}